@@ -0,0 +1,295 @@
+// Size-bounded cache directory with FIFO/LRU eviction.
+use crate::{Error, FileHandler};
+use serde::{de::DeserializeOwned, Serialize};
+use std::fs;
+use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::{debug, instrument, warn};
+
+const METADATA_FILE: &str = ".cache_store_meta";
+
+/// Selects which entry is evicted first once a [`CacheStore`] exceeds its
+/// configured capacity.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EvictionPolicy {
+    /// Evict the entry that was inserted first.
+    Fifo,
+    /// Evict the entry that was accessed least recently.
+    Lru,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+struct EntryMeta {
+    size: u64,
+    last_access: u64,
+    insertion_counter: u64,
+}
+
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+struct StoreMetadata {
+    current_size: u64,
+    counter: u64,
+    entries: Vec<(String, EntryMeta)>,
+}
+
+/// A directory of keyed cache entries bounded to a maximum total size on
+/// disk, evicting the oldest (FIFO) or least recently used (LRU) entry
+/// whenever that budget is exceeded. A sidecar metadata file tracks entry
+/// sizes, access times and insertion order so the store can be reopened
+/// across runs.
+pub struct CacheStore<T> {
+    root: PathBuf,
+    capacity: u64,
+    policy: EvictionPolicy,
+    metadata: StoreMetadata,
+    _marker: PhantomData<T>,
+}
+
+impl<T> CacheStore<T>
+where
+    T: Serialize + DeserializeOwned,
+{
+    /// Opens (or creates) a cache store rooted at `root`, evicting down to
+    /// `capacity` bytes using `policy` whenever a write overflows it.
+    #[instrument(skip(root))]
+    pub fn new<P: AsRef<Path>>(root: P, capacity: u64, policy: EvictionPolicy) -> Self {
+        let root = root.as_ref().to_path_buf();
+        if !root.exists() {
+            debug!("Creating cache store directory at {:?}", root);
+            if let Err(e) = fs::create_dir_all(&root) {
+                warn!("Failed to create cache store directory: {}", e);
+            }
+        }
+        let metadata = Self::load_metadata(&root);
+        Self {
+            root,
+            capacity,
+            policy,
+            metadata,
+            _marker: PhantomData,
+        }
+    }
+
+    fn metadata_path(root: &Path) -> PathBuf {
+        root.join(METADATA_FILE)
+    }
+
+    /// Validates that `key` is a single, plain path component (no
+    /// separators, `.`/`..`, or prefixes), then joins it onto `root`.
+    /// Without this check a key like `"../../etc/cron.d/evil"` would escape
+    /// the store root and let a caller read or write arbitrary files.
+    fn entry_path(&self, key: &str) -> Result<PathBuf, Error> {
+        let mut components = Path::new(key).components();
+        match (components.next(), components.next()) {
+            (Some(std::path::Component::Normal(_)), None) => {}
+            _ => return Err(Error::Other(format!("invalid cache store key: {:?}", key))),
+        }
+        Ok(self.root.join(key))
+    }
+
+    fn load_metadata(root: &Path) -> StoreMetadata {
+        match fs::read(Self::metadata_path(root)) {
+            Ok(bytes) => bincode::deserialize(&bytes).unwrap_or_default(),
+            Err(_) => StoreMetadata::default(),
+        }
+    }
+
+    fn save_metadata(&self) -> Result<(), Error> {
+        let bytes = bincode::serialize(&self.metadata).map_err(Error::Serialize)?;
+        let file_handler = FileHandler::new(Self::metadata_path(&self.root));
+        file_handler.write_atomic(&bytes)?;
+        Ok(())
+    }
+
+    fn now_secs() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0)
+    }
+
+    fn find_index(&self, key: &str) -> Option<usize> {
+        self.metadata.entries.iter().position(|(k, _)| k == key)
+    }
+
+    /// Inserts or replaces `value` under `key`, then evicts entries per the
+    /// configured [`EvictionPolicy`] until the store fits within capacity.
+    #[instrument(skip(self, value))]
+    pub fn set(&mut self, key: &str, value: &T) -> Result<(), Error> {
+        let bytes = bincode::serialize(value).map_err(Error::Serialize)?;
+        let size = bytes.len() as u64;
+        let file_handler = FileHandler::new(self.entry_path(key)?);
+        file_handler.write(&bytes)?;
+
+        if let Some(idx) = self.find_index(key) {
+            self.metadata.current_size -= self.metadata.entries[idx].1.size;
+            self.metadata.entries.remove(idx);
+        }
+
+        self.metadata.counter += 1;
+        self.metadata.entries.push((
+            key.to_string(),
+            EntryMeta {
+                size,
+                last_access: Self::now_secs(),
+                insertion_counter: self.metadata.counter,
+            },
+        ));
+        self.metadata.current_size += size;
+
+        self.evict_if_needed();
+        self.save_metadata()?;
+        debug!("Stored entry {:?} ({} bytes)", key, size);
+        Ok(())
+    }
+
+    /// Ties in `last_access` (`now_secs()` only has one-second resolution)
+    /// are broken by `insertion_counter`, so the entry touched least
+    /// recently wins rather than an arbitrary one.
+    fn evict_if_needed(&mut self) {
+        while self.metadata.current_size > self.capacity && !self.metadata.entries.is_empty() {
+            let victim = match self.policy {
+                // `entries` stays in insertion order (`get` refreshes
+                // `insertion_counter` but never moves an entry), so the
+                // entry inserted first is always at the front.
+                EvictionPolicy::Fifo => Some(0),
+                EvictionPolicy::Lru => self
+                    .metadata
+                    .entries
+                    .iter()
+                    .enumerate()
+                    .min_by_key(|(_, (_, meta))| (meta.last_access, meta.insertion_counter))
+                    .map(|(i, _)| i),
+            };
+            let Some(idx) = victim else { break };
+            let (key, meta) = self.metadata.entries.remove(idx);
+            self.metadata.current_size = self.metadata.current_size.saturating_sub(meta.size);
+            // Keys are validated by `entry_path` before ever being inserted
+            // into `metadata.entries`, so this can only fail if the sidecar
+            // file was tampered with out-of-band.
+            match self.entry_path(&key) {
+                Ok(path) => match fs::remove_file(&path) {
+                    Ok(()) => {}
+                    Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                    Err(e) => warn!("Failed to remove evicted entry {:?}: {}", key, e),
+                },
+                Err(e) => warn!("Skipping evicted entry with invalid key {:?}: {}", key, e),
+            }
+            debug!("Evicted entry {:?} ({} bytes)", key, meta.size);
+        }
+    }
+
+    /// Fetches `key`, bumping its recency metadata on a hit. Returns `None`
+    /// if the key is unknown or its file has gone missing out-of-band.
+    #[instrument(skip(self))]
+    pub fn get(&mut self, key: &str) -> Result<Option<T>, Error> {
+        let path = self.entry_path(key)?;
+        let Some(idx) = self.find_index(key) else {
+            return Ok(None);
+        };
+        if !path.exists() {
+            self.metadata.current_size -= self.metadata.entries[idx].1.size;
+            self.metadata.entries.remove(idx);
+            self.save_metadata()?;
+            return Ok(None);
+        }
+
+        let mut file_handler = FileHandler::new(path);
+        let bytes = file_handler.read()?;
+        let value = bincode::deserialize(&bytes).map_err(Error::Deserialize)?;
+
+        self.metadata.counter += 1;
+        self.metadata.entries[idx].1.last_access = Self::now_secs();
+        self.metadata.entries[idx].1.insertion_counter = self.metadata.counter;
+        self.save_metadata()?;
+
+        Ok(Some(value))
+    }
+
+    /// Total size in bytes of all entries currently tracked by the store.
+    pub fn current_size(&self) -> u64 {
+        self.metadata.current_size
+    }
+
+    /// Number of entries currently tracked by the store.
+    pub fn len(&self) -> usize {
+        self.metadata.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.metadata.entries.is_empty()
+    }
+
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.find_index(key).is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_set_get_roundtrip() {
+        let dir = tempdir().unwrap();
+        let mut store: CacheStore<String> = CacheStore::new(dir.path(), 1024, EvictionPolicy::Fifo);
+
+        store.set("a", &"hello".to_string()).unwrap();
+        assert_eq!(store.get("a").unwrap(), Some("hello".to_string()));
+        assert_eq!(store.get("missing").unwrap(), None);
+
+        dir.close().unwrap();
+    }
+
+    #[test]
+    fn test_fifo_eviction() {
+        let dir = tempdir().unwrap();
+        // Each 4-byte string serializes to 12 bytes (8-byte length prefix +
+        // bytes); cap tightly so the third insert forces an eviction of the
+        // first.
+        let mut store: CacheStore<String> = CacheStore::new(dir.path(), 24, EvictionPolicy::Fifo);
+
+        store.set("a", &"aaaa".to_string()).unwrap();
+        store.set("b", &"bbbb".to_string()).unwrap();
+        store.set("c", &"cccc".to_string()).unwrap();
+
+        assert!(!store.contains_key("a"));
+        assert!(store.contains_key("c"));
+        assert!(store.current_size() <= 24);
+
+        dir.close().unwrap();
+    }
+
+    #[test]
+    fn test_lru_eviction_prefers_unaccessed() {
+        let dir = tempdir().unwrap();
+        let mut store: CacheStore<String> = CacheStore::new(dir.path(), 24, EvictionPolicy::Lru);
+
+        store.set("a", &"aaaa".to_string()).unwrap();
+        store.set("b", &"bbbb".to_string()).unwrap();
+        // Touch "a" so "b" becomes the least recently used entry.
+        store.get("a").unwrap();
+        store.set("c", &"cccc".to_string()).unwrap();
+
+        assert!(store.contains_key("a"));
+        assert!(!store.contains_key("b"));
+
+        dir.close().unwrap();
+    }
+
+    #[test]
+    fn test_rejects_path_traversal_keys() {
+        let dir = tempdir().unwrap();
+        let mut store: CacheStore<String> = CacheStore::new(dir.path(), 1024, EvictionPolicy::Fifo);
+
+        assert!(store.set("../escape", &"x".to_string()).is_err());
+        assert!(store.set("a/../../escape", &"x".to_string()).is_err());
+        assert!(store.set("a/b", &"x".to_string()).is_err());
+        assert!(store.get("../escape").is_err());
+        assert!(!dir.path().parent().unwrap().join("escape").exists());
+
+        dir.close().unwrap();
+    }
+}
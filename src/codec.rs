@@ -0,0 +1,100 @@
+// Pluggable (de)serialization formats shared by CacheFile and CacheConfig.
+use crate::Error;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+/// Converts a value to and from bytes for on-disk storage. `CacheFile<T>`
+/// defaults to [`BincodeCodec`] and `CacheConfig<T>` to [`JsonCodec`]; either
+/// can be swapped via `with_codec` to change the on-disk format without
+/// touching the rest of the caching logic.
+pub trait Codec<T> {
+    /// Serializes `value` into bytes for on-disk storage.
+    fn encode(&self, value: &T) -> Result<Vec<u8>, Error>;
+
+    /// Deserializes bytes previously produced by [`encode`](Self::encode)
+    /// back into `T`.
+    fn decode(&self, bytes: &[u8]) -> Result<T, Error>;
+}
+
+/// Bincode's compact positional binary format. The default codec for
+/// [`CacheFile`](crate::CacheFile).
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BincodeCodec;
+
+impl<T> Codec<T> for BincodeCodec
+where
+    T: Serialize + DeserializeOwned,
+{
+    fn encode(&self, value: &T) -> Result<Vec<u8>, Error> {
+        bincode::serialize(value).map_err(Error::Serialize)
+    }
+
+    fn decode(&self, bytes: &[u8]) -> Result<T, Error> {
+        bincode::deserialize(bytes).map_err(Error::Deserialize)
+    }
+}
+
+/// Human-readable JSON. The default codec for [`CacheConfig`](crate::CacheConfig).
+#[derive(Debug, Clone, Copy, Default)]
+pub struct JsonCodec;
+
+impl<T> Codec<T> for JsonCodec
+where
+    T: Serialize + DeserializeOwned,
+{
+    fn encode(&self, value: &T) -> Result<Vec<u8>, Error> {
+        serde_json::to_vec(value).map_err(Error::Json)
+    }
+
+    fn decode(&self, bytes: &[u8]) -> Result<T, Error> {
+        serde_json::from_slice(bytes).map_err(Error::Json)
+    }
+}
+
+/// Compact, self-describing MessagePack via `rmp_serde`. Structs are encoded
+/// as length-prefixed arrays, so unlike bincode's positional layout, data
+/// written before the cached type gained a trailing `#[serde(default)]` field
+/// still decodes; a new field without a default still fails to decode.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MessagePackCodec;
+
+impl<T> Codec<T> for MessagePackCodec
+where
+    T: Serialize + DeserializeOwned,
+{
+    fn encode(&self, value: &T) -> Result<Vec<u8>, Error> {
+        rmp_serde::to_vec(value).map_err(Error::MessagePackEncode)
+    }
+
+    fn decode(&self, bytes: &[u8]) -> Result<T, Error> {
+        rmp_serde::from_slice(bytes).map_err(Error::MessagePackDecode)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bincode_codec_roundtrip() {
+        let codec = BincodeCodec;
+        let bytes = Codec::<i32>::encode(&codec, &42).unwrap();
+        assert_eq!(Codec::<i32>::decode(&codec, &bytes).unwrap(), 42);
+    }
+
+    #[test]
+    fn test_json_codec_roundtrip() {
+        let codec = JsonCodec;
+        let value = "hello".to_string();
+        let bytes = Codec::<String>::encode(&codec, &value).unwrap();
+        assert_eq!(Codec::<String>::decode(&codec, &bytes).unwrap(), "hello");
+    }
+
+    #[test]
+    fn test_messagepack_codec_roundtrip() {
+        let codec = MessagePackCodec;
+        let value = vec![1, 2, 3];
+        let bytes = Codec::<Vec<i32>>::encode(&codec, &value).unwrap();
+        assert_eq!(Codec::<Vec<i32>>::decode(&codec, &bytes).unwrap(), vec![1, 2, 3]);
+    }
+}
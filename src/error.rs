@@ -0,0 +1,50 @@
+// Crate-level error type shared by CacheFile, CacheConfig and FileHandler.
+use std::path::PathBuf;
+use thiserror::Error;
+
+/// The error type returned by this crate's fallible operations. Replaces the
+/// old `Box<dyn std::error::Error>` so callers can match on failure modes
+/// instead of string-matching.
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("failed to serialize data: {0}")]
+    Serialize(bincode::Error),
+
+    #[error("failed to deserialize data: {0}")]
+    Deserialize(bincode::Error),
+
+    #[error("JSON serialization error: {0}")]
+    Json(#[from] serde_json::Error),
+
+    #[error("MessagePack serialization error: {0}")]
+    MessagePackEncode(#[from] rmp_serde::encode::Error),
+
+    #[error("MessagePack deserialization error: {0}")]
+    MessagePackDecode(#[from] rmp_serde::decode::Error),
+
+    #[error("file not found: {0}")]
+    NotFound(PathBuf),
+
+    #[error("{0}")]
+    Other(String),
+}
+
+impl Error {
+    /// Returns `true` if this error represents a missing file rather than a
+    /// genuine I/O failure, so callers don't need to inspect `io::Error`
+    /// kinds themselves. Covers both the explicit [`Error::NotFound`]
+    /// variant and a plain [`Error::Io`] wrapping `ErrorKind::NotFound`,
+    /// since most call sites reach a missing file through the blanket
+    /// `#[from] io::Error` conversion rather than constructing `NotFound`
+    /// directly.
+    pub fn is_not_found(&self) -> bool {
+        match self {
+            Error::NotFound(_) => true,
+            Error::Io(e) => e.kind() == std::io::ErrorKind::NotFound,
+            _ => false,
+        }
+    }
+}
@@ -1,7 +1,17 @@
 // LDC - Local Data Cache
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, RwLock, RwLockReadGuard};
+use std::time::{Duration, SystemTime};
 use tracing::{debug, error, info, instrument, warn};
 
+mod cache_store;
+mod codec;
+mod error;
+pub use cache_store::{CacheStore, EvictionPolicy};
+pub use codec::{BincodeCodec, Codec, JsonCodec, MessagePackCodec};
+pub use error::Error;
+
 #[instrument]
 pub fn cache_file_string(path: &str) -> CacheFile<String> {
     debug!("Creating string cache file at {}", path);
@@ -40,19 +50,83 @@ pub fn cache_folder(path: &str) -> PathBuf {
     path_buf
 }
 
-pub struct CacheFile<T> {
-    file_handler: FileHandler,
-    data_type: T,
+/// Number of [`CacheFile::update`] calls between automatic flushes to disk,
+/// unless overridden with [`CacheFile::set_autosave_threshold`].
+const DEFAULT_AUTOSAVE_THRESHOLD: usize = 10;
+
+pub struct CacheFile<T>
+where
+    T: serde::Serialize + serde::de::DeserializeOwned,
+{
+    file_handler: Mutex<FileHandler>,
+    data_type: RwLock<T>,
+    ttl: Option<Duration>,
+    compressed: bool,
+    autosave_threshold: usize,
+    pending_writes: AtomicUsize,
+    codec: Box<dyn Codec<T> + Send + Sync>,
+}
+
+/// Magic byte prefixed to DEFLATE-compressed payloads so `read`/`new` can tell
+/// compressed data apart from plain bincode written before this feature (or
+/// by a `CacheFile` that isn't using [`compressed`](CacheFile::compressed)).
+const COMPRESSION_MAGIC: u8 = 0xCE;
+
+fn compress_payload(bytes: &[u8]) -> Result<Vec<u8>, Error> {
+    use flate2::write::DeflateEncoder;
+    use flate2::Compression;
+    use std::io::Write as _;
+
+    let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(bytes)?;
+    let mut out = Vec::with_capacity(bytes.len() + 1);
+    out.push(COMPRESSION_MAGIC);
+    out.extend(encoder.finish()?);
+    Ok(out)
+}
+
+fn decompress_payload(bytes: &[u8]) -> Result<Vec<u8>, Error> {
+    use flate2::read::DeflateDecoder;
+    use std::io::Read as _;
+
+    match bytes.split_first() {
+        Some((&magic, rest)) if magic == COMPRESSION_MAGIC => {
+            let mut decoder = DeflateDecoder::new(rest);
+            let mut out = Vec::new();
+            decoder.read_to_end(&mut out)?;
+            Ok(out)
+        }
+        _ => Ok(bytes.to_vec()),
+    }
 }
 
 impl<T> CacheFile<T>
 where
     T: serde::Serialize + serde::de::DeserializeOwned,
 {
+    /// Creates a cache file backed by `file_handler` using the default
+    /// [`BincodeCodec`]. Combine with the builder methods
+    /// [`compressed`](Self::compressed) and [`with_ttl`](Self::with_ttl) (or
+    /// start from [`with_codec`](Self::with_codec) for a different format)
+    /// to opt into those features on top of it, e.g.
+    /// `CacheFile::with_codec(fh, MessagePackCodec).compressed().with_ttl(ttl)`.
     #[instrument(skip(file_handler))]
     pub fn new(file_handler: FileHandler) -> Self
     where
         T: Default,
+    {
+        Self::with_codec(file_handler, BincodeCodec)
+    }
+
+    /// Creates a cache file that uses `codec` to (de)serialize its value
+    /// instead of the default [`BincodeCodec`]. Useful for picking a format
+    /// (e.g. [`JsonCodec`] or [`MessagePackCodec`]) that tolerates the cached
+    /// type gaining fields between runs.
+    #[instrument(skip(file_handler, codec))]
+    pub fn with_codec<C>(file_handler: FileHandler, codec: C) -> Self
+    where
+        T: Default,
+        C: Codec<T> + Send + Sync + 'static,
     {
         debug!(
             "Initializing new cache file at {:?}",
@@ -60,7 +134,7 @@ where
         );
         let mut file_handler = file_handler;
         let data_type = match file_handler.read() {
-            Ok(bytes) => match bincode::deserialize(&bytes) {
+            Ok(bytes) => match decompress_payload(&bytes).and_then(|b| codec.decode(&b)) {
                 Ok(data) => {
                     debug!("Successfully loaded existing data");
                     data
@@ -76,57 +150,184 @@ where
             }
         };
         Self {
-            file_handler,
-            data_type,
+            file_handler: Mutex::new(file_handler),
+            data_type: RwLock::new(data_type),
+            ttl: None,
+            compressed: false,
+            autosave_threshold: DEFAULT_AUTOSAVE_THRESHOLD,
+            pending_writes: AtomicUsize::new(0),
+            codec: Box::new(codec),
+        }
+    }
+
+    /// Enables DEFLATE compression of the serialized payload on
+    /// [`write`](Self::write)/[`flush`](Self::flush). Existing uncompressed
+    /// files (and files written by other `CacheFile`s) still load correctly:
+    /// a magic byte prefix lets `read` and `new` detect whether
+    /// decompression is needed.
+    ///
+    /// Chainable with the other builder methods, e.g.
+    /// `CacheFile::with_codec(fh, MessagePackCodec).compressed().with_ttl(ttl)`.
+    pub fn compressed(mut self) -> Self {
+        self.compressed = true;
+        self
+    }
+
+    /// Sets a TTL after which on-disk data is treated as stale: if the
+    /// underlying file's `modified()` timestamp is already older than `ttl`,
+    /// the loaded data is discarded and reset to `T::default()` immediately.
+    ///
+    /// Chainable with the other builder methods, e.g.
+    /// `CacheFile::with_codec(fh, MessagePackCodec).compressed().with_ttl(ttl)`.
+    pub fn with_ttl(mut self, ttl: Duration) -> Self
+    where
+        T: Default,
+    {
+        self.ttl = Some(ttl);
+        if self.is_expired() {
+            debug!("Existing cache data is already expired, resetting to default");
+            *self.write_guard() = T::default();
         }
+        self
+    }
+
+    /// Overrides how many [`update`](Self::update) calls are allowed before
+    /// the cache is automatically flushed to disk. The default is
+    /// [`DEFAULT_AUTOSAVE_THRESHOLD`](self::DEFAULT_AUTOSAVE_THRESHOLD).
+    pub fn set_autosave_threshold(&mut self, threshold: usize) {
+        self.autosave_threshold = threshold;
+    }
+
+    /// Returns `true` if this cache file has a TTL and the underlying file's
+    /// `modified()` timestamp is older than that TTL. A cache file with no TTL
+    /// never expires.
+    pub fn is_expired(&self) -> bool {
+        let Some(ttl) = self.ttl else {
+            return false;
+        };
+        let handler = self.lock_handler();
+        let Ok(metadata) = handler.metadata() else {
+            return false;
+        };
+        let Ok(modified) = metadata.modified() else {
+            return false;
+        };
+        match SystemTime::now().duration_since(modified) {
+            Ok(age) => age > ttl,
+            Err(_) => false,
+        }
+    }
+
+    fn lock_handler(&self) -> std::sync::MutexGuard<'_, FileHandler> {
+        self.file_handler
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn read_guard(&self) -> RwLockReadGuard<'_, T> {
+        self.data_type
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn write_guard(&self) -> std::sync::RwLockWriteGuard<'_, T> {
+        self.data_type
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Returns a read guard over the cached value. Safe to call from multiple
+    /// threads sharing this `CacheFile` behind an `Arc`.
+    pub fn get(&self) -> RwLockReadGuard<'_, T> {
+        self.read_guard()
     }
 
-    pub fn get_data(&self) -> &T {
-        &self.data_type
+    /// Applies `f` to the cached value under a write lock. After
+    /// [`autosave_threshold`](Self::set_autosave_threshold) mutations have
+    /// accumulated, the cache is flushed to disk automatically.
+    pub fn update<F>(&self, f: F) -> Result<(), Error>
+    where
+        F: FnOnce(&mut T),
+    {
+        {
+            let mut guard = self.write_guard();
+            f(&mut guard);
+        }
+        let pending = self.pending_writes.fetch_add(1, Ordering::SeqCst) + 1;
+        if pending >= self.autosave_threshold {
+            self.flush()?;
+        }
+        Ok(())
     }
 
-    pub fn get_mut_data(&mut self) -> &mut T {
-        &mut self.data_type
+    /// Serializes the current value to disk immediately and resets the
+    /// autosave counter. Called automatically by [`update`](Self::update)
+    /// once the threshold is reached, and by `Drop` if writes are pending.
+    #[instrument(skip(self))]
+    pub fn flush(&self) -> Result<(), Error> {
+        debug!("Flushing cache file");
+        let bytes = self.codec.encode(&*self.read_guard())?;
+        let bytes = if self.compressed {
+            compress_payload(&bytes)?
+        } else {
+            bytes
+        };
+        self.lock_handler().write_atomic(&bytes)?;
+        self.pending_writes.store(0, Ordering::SeqCst);
+        debug!("Successfully flushed cache file");
+        Ok(())
     }
 
     #[instrument(skip(self))]
-    pub fn read(&mut self) -> Result<T, Box<dyn std::error::Error>>
+    pub fn read(&self) -> Result<T, Error>
     where
         T: Clone,
     {
         debug!("Reading from cache file");
-        let bytes = self.file_handler.read()?;
-        match bincode::deserialize(&bytes) {
+        let bytes = self.lock_handler().read()?;
+        let bytes = decompress_payload(&bytes)?;
+        match self.codec.decode(&bytes) {
             Ok(data) => {
-                self.data_type = data;
+                *self.write_guard() = data;
                 debug!("Successfully read data from cache");
-                Ok(self.data_type.clone())
+                Ok(self.read_guard().clone())
             }
             Err(e) => {
                 error!("Failed to deserialize cache data: {}", e);
-                Err(Box::new(e))
+                Err(e)
             }
         }
     }
 
+    /// Writes the current value to disk immediately, bypassing the autosave
+    /// counter. Equivalent to [`flush`](Self::flush).
     #[instrument(skip(self))]
-    pub fn write(&self) -> Result<(), Box<dyn std::error::Error>> {
-        debug!("Writing to cache file");
-        match bincode::serialize(&self.data_type) {
-            Ok(bytes) => {
-                self.file_handler.write(&bytes)?;
-                debug!("Successfully wrote data to cache");
-                Ok(())
-            }
-            Err(e) => {
-                error!("Failed to serialize data: {}", e);
-                Err(Box::new(e))
+    pub fn write(&self) -> Result<(), Error> {
+        self.flush()
+    }
+
+    /// Like [`read`](Self::read), but TTL-aware: if this cache file has expired,
+    /// the stale data is dropped and the underlying file deleted instead of
+    /// being returned.
+    #[instrument(skip(self))]
+    pub fn read_fresh(&self) -> Result<Option<T>, Error>
+    where
+        T: Clone + Default,
+    {
+        if self.is_expired() {
+            debug!("Cache file is expired, discarding stale data");
+            *self.write_guard() = T::default();
+            let handler = self.lock_handler();
+            if handler.exists() {
+                handler.delete()?;
             }
+            return Ok(None);
         }
+        self.read().map(Some)
     }
 
     #[instrument(skip(self))]
-    pub fn append(&mut self, data_type: &T) -> Result<(), Box<dyn std::error::Error>>
+    pub fn append(&self, data_type: &T) -> Result<(), Error>
     where
         T: std::fmt::Display + std::str::FromStr + Clone + std::fmt::Debug,
         <T as std::str::FromStr>::Err: std::error::Error + 'static,
@@ -137,34 +338,49 @@ where
         current_str.push_str(&data_type.to_string());
         match current_str.parse() {
             Ok(parsed) => {
-                self.data_type = parsed;
-                self.write()?;
+                *self.write_guard() = parsed;
+                self.flush()?;
                 debug!("Successfully appended data to cache");
                 Ok(())
             }
             Err(e) => {
                 error!("Failed to parse appended data: {}", e);
-                Err(Box::new(e))
+                Err(Error::Other(e.to_string()))
             }
         }
     }
 
     #[instrument(skip(self))]
-    pub fn delete(&self) -> Result<(), Box<dyn std::error::Error>> {
+    pub fn delete(&self) -> Result<(), Error> {
         debug!("Deleting cache file");
-        self.file_handler.delete()?;
+        self.lock_handler().delete()?;
         debug!("Successfully deleted cache file");
         Ok(())
     }
 
     pub fn exists(&self) -> bool {
-        self.file_handler.exists()
+        self.lock_handler().exists()
+    }
+}
+
+impl<T> Drop for CacheFile<T>
+where
+    T: serde::Serialize + serde::de::DeserializeOwned,
+{
+    fn drop(&mut self) {
+        if self.pending_writes.load(Ordering::SeqCst) == 0 {
+            return;
+        }
+        if let Err(e) = self.flush() {
+            error!("Failed to flush pending writes on drop: {}", e);
+        }
     }
 }
 
 pub struct CacheConfig<T> {
     file_handler: FileHandler,
     config: T,
+    codec: Box<dyn Codec<T> + Send + Sync>,
 }
 
 impl<T> CacheConfig<T>
@@ -175,6 +391,17 @@ where
     pub fn new(file_handler: FileHandler) -> Self
     where
         T: Default,
+    {
+        Self::with_codec(file_handler, JsonCodec)
+    }
+
+    /// Creates a config file that uses `codec` to (de)serialize the config
+    /// instead of the default [`JsonCodec`].
+    #[instrument(skip(file_handler, codec))]
+    pub fn with_codec<C>(file_handler: FileHandler, codec: C) -> Self
+    where
+        T: Default,
+        C: Codec<T> + Send + Sync + 'static,
     {
         debug!(
             "Initializing new config file at {:?}",
@@ -182,7 +409,7 @@ where
         );
         let mut file_handler = file_handler;
         let config = match file_handler.read() {
-            Ok(bytes) => match serde_json::from_slice(&bytes) {
+            Ok(bytes) => match codec.decode(&bytes) {
                 Ok(config) => {
                     debug!("Successfully loaded existing config");
                     config
@@ -200,6 +427,7 @@ where
         Self {
             file_handler,
             config,
+            codec: Box::new(codec),
         }
     }
 
@@ -212,13 +440,13 @@ where
     }
 
     #[instrument(skip(self))]
-    pub fn read(&mut self) -> Result<T, Box<dyn std::error::Error>>
+    pub fn read(&mut self) -> Result<T, Error>
     where
         T: Clone,
     {
         debug!("Reading config file");
         let bytes = self.file_handler.read()?;
-        match serde_json::from_slice(&bytes) {
+        match self.codec.decode(&bytes) {
             Ok(config) => {
                 self.config = config;
                 debug!("Successfully read config");
@@ -226,29 +454,29 @@ where
             }
             Err(e) => {
                 error!("Failed to deserialize config: {}", e);
-                Err(Box::new(e))
+                Err(e)
             }
         }
     }
 
     #[instrument(skip(self))]
-    pub fn write(&self) -> Result<(), Box<dyn std::error::Error>> {
+    pub fn write(&self) -> Result<(), Error> {
         debug!("Writing config file");
-        match serde_json::to_string(&self.config) {
-            Ok(json_str) => {
-                self.file_handler.write(json_str.as_bytes())?;
+        match self.codec.encode(&self.config) {
+            Ok(bytes) => {
+                self.file_handler.write(&bytes)?;
                 debug!("Successfully wrote config");
                 Ok(())
             }
             Err(e) => {
                 error!("Failed to serialize config: {}", e);
-                Err(Box::new(e))
+                Err(e)
             }
         }
     }
 
     #[instrument(skip(self))]
-    pub fn delete(&self) -> Result<(), Box<dyn std::error::Error>> {
+    pub fn delete(&self) -> Result<(), Error> {
         debug!("Deleting config file");
         self.file_handler.delete()?;
         debug!("Successfully deleted config file");
@@ -270,9 +498,9 @@ mod tests {
         let dir = tempdir().unwrap();
         let file_path = dir.path().join("string.txt");
         let file_handler = FileHandler::new(file_path.to_str().unwrap().to_string());
-        let mut cache = CacheFile::<String>::new(file_handler);
+        let cache = CacheFile::<String>::new(file_handler);
 
-        *cache.get_mut_data() = String::from("test");
+        cache.update(|d| *d = String::from("test")).unwrap();
         cache.write().unwrap();
         assert_eq!(cache.read().unwrap(), "test");
         cache.delete().unwrap();
@@ -284,9 +512,9 @@ mod tests {
         let dir = tempdir().unwrap();
         let file_path = dir.path().join("int.txt");
         let file_handler = FileHandler::new(file_path.to_str().unwrap().to_string());
-        let mut cache = CacheFile::<i32>::new(file_handler);
+        let cache = CacheFile::<i32>::new(file_handler);
 
-        *cache.get_mut_data() = 42;
+        cache.update(|d| *d = 42).unwrap();
         cache.write().unwrap();
         assert_eq!(cache.read().unwrap(), 42);
         cache.delete().unwrap();
@@ -298,9 +526,9 @@ mod tests {
         let dir = tempdir().unwrap();
         let file_path = dir.path().join("float.txt");
         let file_handler = FileHandler::new(file_path.to_str().unwrap().to_string());
-        let mut cache = CacheFile::<f64>::new(file_handler);
+        let cache = CacheFile::<f64>::new(file_handler);
 
-        *cache.get_mut_data() = 3.14;
+        cache.update(|d| *d = 3.14).unwrap();
         cache.write().unwrap();
         assert_eq!(cache.read().unwrap(), 3.14);
         cache.delete().unwrap();
@@ -312,18 +540,152 @@ mod tests {
         let dir = tempdir().unwrap();
         let file_path = dir.path().join("bool.txt");
         let file_handler = FileHandler::new(file_path.to_str().unwrap().to_string());
-        let mut cache = CacheFile::<bool>::new(file_handler);
+        let cache = CacheFile::<bool>::new(file_handler);
 
-        *cache.get_mut_data() = true;
+        cache.update(|d| *d = true).unwrap();
         cache.write().unwrap();
         assert_eq!(cache.read().unwrap(), true);
         cache.delete().unwrap();
         dir.close().unwrap();
     }
+
+    #[test]
+    fn test_ttl_expiration() {
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join("ttl.txt");
+        let file_handler = FileHandler::new(file_path.to_str().unwrap().to_string());
+        let cache = CacheFile::<String>::new(file_handler).with_ttl(Duration::from_millis(50));
+
+        cache.update(|d| *d = String::from("fresh")).unwrap();
+        cache.write().unwrap();
+        assert!(!cache.is_expired());
+        assert_eq!(cache.read_fresh().unwrap(), Some(String::from("fresh")));
+
+        std::thread::sleep(Duration::from_millis(100));
+        assert!(cache.is_expired());
+        assert_eq!(cache.read_fresh().unwrap(), None);
+        assert!(!cache.exists());
+
+        dir.close().unwrap();
+    }
+
+    #[test]
+    fn test_atomic_write_leaves_no_temp_file() {
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join("atomic.txt");
+        let file_handler = FileHandler::new(&file_path);
+
+        file_handler.write_atomic(b"durable").unwrap();
+        assert_eq!(fs::read(&file_path).unwrap(), b"durable");
+
+        let leftovers: Vec<_> = fs::read_dir(dir.path())
+            .unwrap()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_name().to_string_lossy().contains(".tmp."))
+            .collect();
+        assert!(leftovers.is_empty());
+
+        dir.close().unwrap();
+    }
+
+    #[test]
+    fn test_compressed_cache_roundtrip() {
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join("compressed.txt");
+        let file_handler = FileHandler::new(file_path.to_str().unwrap().to_string());
+        let cache = CacheFile::<String>::new(file_handler).compressed();
+
+        let payload = "x".repeat(1024);
+        cache.update(|d| *d = payload.clone()).unwrap();
+        cache.write().unwrap();
+        assert_eq!(cache.read().unwrap(), payload);
+
+        // Reopening (even without the `compressed` flag) must still detect the
+        // magic byte and decompress correctly.
+        let reopened_handler = FileHandler::new(file_path.to_str().unwrap().to_string());
+        let reopened = CacheFile::<String>::new(reopened_handler);
+        assert_eq!(*reopened.get(), payload);
+        assert_eq!(reopened.read().unwrap(), payload);
+
+        dir.close().unwrap();
+    }
+
+    #[test]
+    fn test_autosave_flushes_after_threshold() {
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join("autosave.txt");
+        let file_handler = FileHandler::new(file_path.to_str().unwrap().to_string());
+        let mut cache = CacheFile::<i32>::new(file_handler);
+        cache.set_autosave_threshold(3);
+
+        cache.update(|d| *d = 1).unwrap();
+        cache.update(|d| *d = 2).unwrap();
+        // Not flushed yet: file shouldn't exist on disk.
+        assert!(!file_path.exists());
+
+        cache.update(|d| *d = 3).unwrap();
+        // Third mutation crosses the threshold and triggers an automatic flush.
+        assert!(file_path.exists());
+
+        let reopened_handler = FileHandler::new(file_path.to_str().unwrap().to_string());
+        let reopened = CacheFile::<i32>::new(reopened_handler);
+        assert_eq!(*reopened.get(), 3);
+
+        dir.close().unwrap();
+    }
+
+    #[test]
+    fn test_with_codec_messagepack_roundtrip() {
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join("msgpack.txt");
+        let file_handler = FileHandler::new(file_path.to_str().unwrap().to_string());
+        let cache = CacheFile::<String>::with_codec(file_handler, MessagePackCodec);
+
+        cache.update(|d| *d = String::from("packed")).unwrap();
+        cache.write().unwrap();
+        assert_eq!(cache.read().unwrap(), "packed");
+
+        dir.close().unwrap();
+    }
+
+    #[test]
+    fn test_composed_codec_compressed_and_ttl() {
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join("composed.txt");
+        let file_handler = FileHandler::new(file_path.to_str().unwrap().to_string());
+        let cache = CacheFile::<String>::with_codec(file_handler, MessagePackCodec)
+            .compressed()
+            .with_ttl(Duration::from_secs(60));
+
+        let payload = "y".repeat(1024);
+        cache.update(|d| *d = payload.clone()).unwrap();
+        cache.write().unwrap();
+        assert!(!cache.is_expired());
+        assert_eq!(cache.read_fresh().unwrap(), Some(payload));
+
+        dir.close().unwrap();
+    }
+
+    #[test]
+    fn test_drop_flushes_pending_writes() {
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join("drop_flush.txt");
+        {
+            let file_handler = FileHandler::new(file_path.to_str().unwrap().to_string());
+            let cache = CacheFile::<i32>::new(file_handler);
+            cache.update(|d| *d = 7).unwrap();
+            // Dropped here with one pending write and no explicit flush.
+        }
+
+        let reopened_handler = FileHandler::new(file_path.to_str().unwrap().to_string());
+        let reopened = CacheFile::<i32>::new(reopened_handler);
+        assert_eq!(*reopened.get(), 7);
+
+        dir.close().unwrap();
+    }
 }
 
 // FileHandler
-use std::error::Error;
 use std::fs::{self, File};
 use std::io::{BufReader, BufWriter, Read, Write};
 use std::path::Path;
@@ -334,6 +696,34 @@ pub struct FileHandler {
     content: Option<Vec<u8>>,
 }
 
+/// Removes the temp file it guards on drop unless [`commit`](Self::commit) was
+/// called, so a failed atomic write never leaves a stray `.tmp.*` file behind.
+struct TempFileGuard {
+    path: PathBuf,
+    committed: bool,
+}
+
+impl TempFileGuard {
+    fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            committed: false,
+        }
+    }
+
+    fn commit(mut self) {
+        self.committed = true;
+    }
+}
+
+impl Drop for TempFileGuard {
+    fn drop(&mut self) {
+        if !self.committed {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+}
+
 impl FileHandler {
     pub fn new<P: AsRef<Path>>(path: P) -> Self {
         Self {
@@ -343,9 +733,15 @@ impl FileHandler {
     }
 
     #[instrument(skip(self))]
-    pub fn read(&mut self) -> Result<Vec<u8>, Box<dyn Error>> {
+    pub fn read(&mut self) -> Result<Vec<u8>, Error> {
         debug!("Reading file: {:?}", self.path);
-        let file = File::open(&self.path)?;
+        let file = File::open(&self.path).map_err(|e| {
+            if e.kind() == std::io::ErrorKind::NotFound {
+                Error::NotFound(self.path.clone())
+            } else {
+                Error::Io(e)
+            }
+        })?;
         let mut reader = BufReader::new(file);
         let mut content = Vec::new();
         reader.read_to_end(&mut content)?;
@@ -354,18 +750,60 @@ impl FileHandler {
         Ok(content)
     }
 
+    /// Writes `content` to the target path. This goes through
+    /// [`write_atomic`](Self::write_atomic) so readers never observe a
+    /// partially-written file, even if the process crashes mid-write.
+    #[instrument(skip(self, content))]
+    pub fn write(&self, content: &[u8]) -> Result<(), Error> {
+        self.write_atomic(content)
+    }
+
+    /// Writes `content` to the target path by writing to a sibling temp file,
+    /// flushing and `fsync`-ing it, then `rename`-ing it over the final path.
+    /// Renaming within the same directory is atomic on the same filesystem, so
+    /// a crash mid-write leaves the old content (or nothing) instead of a
+    /// truncated file. The temp file is created with `0o600` permissions on
+    /// Unix so secrets never briefly appear world-readable, and is cleaned up
+    /// on any error path.
     #[instrument(skip(self, content))]
-    pub fn write(&self, content: &[u8]) -> Result<(), Box<dyn Error>> {
-        debug!("Writing {} bytes to {:?}", content.len(), self.path);
-        let file = File::create(&self.path)?;
+    pub fn write_atomic(&self, content: &[u8]) -> Result<(), Error> {
+        debug!("Atomically writing {} bytes to {:?}", content.len(), self.path);
+        let dir = self.path.parent().filter(|p| !p.as_os_str().is_empty());
+        let file_name = self
+            .path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or("cache");
+        let tmp_name = format!("{}.tmp.{}", file_name, std::process::id());
+        let tmp_path = match dir {
+            Some(dir) => dir.join(tmp_name),
+            None => PathBuf::from(tmp_name),
+        };
+
+        let guard = TempFileGuard::new(tmp_path.clone());
+
+        let mut open_options = fs::OpenOptions::new();
+        open_options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            open_options.mode(0o600);
+        }
+        let file = open_options.open(&tmp_path)?;
         let mut writer = BufWriter::new(file);
         writer.write_all(content)?;
-        debug!("Successfully wrote data");
+        writer.flush()?;
+        writer.get_ref().sync_all()?;
+        drop(writer);
+
+        fs::rename(&tmp_path, &self.path)?;
+        guard.commit();
+        debug!("Successfully wrote data atomically");
         Ok(())
     }
 
     #[instrument(skip(self, content))]
-    pub fn append(&self, content: &[u8]) -> Result<(), Box<dyn Error>> {
+    pub fn append(&self, content: &[u8]) -> Result<(), Error> {
         debug!("Appending {} bytes to {:?}", content.len(), self.path);
         let mut file = fs::OpenOptions::new()
             .write(true)
@@ -377,7 +815,7 @@ impl FileHandler {
     }
 
     #[instrument(skip(self))]
-    pub fn delete(&self) -> Result<(), Box<dyn Error>> {
+    pub fn delete(&self) -> Result<(), Error> {
         debug!("Deleting file: {:?}", self.path);
         fs::remove_file(&self.path)?;
         debug!("Successfully deleted file");
@@ -392,7 +830,7 @@ impl FileHandler {
     pub fn copy_to<P: AsRef<Path> + std::fmt::Debug>(
         &self,
         destination: P,
-    ) -> Result<(), Box<dyn Error>> {
+    ) -> Result<(), Error> {
         debug!("Copying {:?} to {:?}", self.path, destination.as_ref());
         fs::copy(&self.path, destination)?;
         debug!("Successfully copied file");
@@ -403,14 +841,14 @@ impl FileHandler {
     pub fn move_to<P: AsRef<Path> + std::fmt::Debug>(
         &self,
         destination: P,
-    ) -> Result<(), Box<dyn Error>> {
+    ) -> Result<(), Error> {
         debug!("Moving {:?} to {:?}", self.path, destination.as_ref());
         fs::rename(&self.path, destination)?;
         debug!("Successfully moved file");
         Ok(())
     }
 
-    pub fn metadata(&self) -> Result<fs::Metadata, Box<dyn Error>> {
+    pub fn metadata(&self) -> Result<fs::Metadata, Error> {
         Ok(fs::metadata(&self.path)?)
     }
 